@@ -0,0 +1,418 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::client::{read_response_body, SAPODataService};
+use crate::error::SapError;
+
+/// One request to bundle into a [`Batch`]: a method, an endpoint relative
+/// to the service root, and an optional JSON body.
+pub struct Operation {
+    method: reqwest::Method,
+    endpoint: String,
+    body: Option<Value>,
+}
+
+impl Operation {
+    pub fn get(endpoint: impl Into<String>) -> Self {
+        Operation {
+            method: reqwest::Method::GET,
+            endpoint: endpoint.into(),
+            body: None,
+        }
+    }
+
+    pub fn post(endpoint: impl Into<String>, body: Value) -> Self {
+        Operation {
+            method: reqwest::Method::POST,
+            endpoint: endpoint.into(),
+            body: Some(body),
+        }
+    }
+
+    pub fn put(endpoint: impl Into<String>, body: Value) -> Self {
+        Operation {
+            method: reqwest::Method::PUT,
+            endpoint: endpoint.into(),
+            body: Some(body),
+        }
+    }
+
+    pub fn delete(endpoint: impl Into<String>) -> Self {
+        Operation {
+            method: reqwest::Method::DELETE,
+            endpoint: endpoint.into(),
+            body: None,
+        }
+    }
+
+    /// Renders this operation as one `application/http` batch part.
+    fn render(&self) -> String {
+        let mut part = String::new();
+        part.push_str("Content-Type: application/http\r\n");
+        part.push_str("Content-Transfer-Encoding: binary\r\n\r\n");
+        part.push_str(&format!("{} {} HTTP/1.1\r\n", self.method, self.endpoint));
+
+        match &self.body {
+            Some(body) => {
+                part.push_str("Content-Type: application/json\r\n\r\n");
+                part.push_str(&body.to_string());
+                part.push_str("\r\n");
+            }
+            None => {
+                part.push_str("Accept: application/json\r\n\r\n");
+            }
+        }
+
+        part
+    }
+}
+
+enum Part {
+    Read(Operation),
+    ChangeSet(Vec<Operation>),
+}
+
+/// Bundles several OData operations into one `$batch` multipart request.
+///
+/// Operations queued with [`Self::read`] are sent directly under the
+/// top-level batch boundary; operations queued together with
+/// [`Self::change_set`] are wrapped in their own nested `multipart/mixed`
+/// group, which SAP commits or rolls back as a single transaction.
+pub struct Batch<'a> {
+    service: &'a SAPODataService,
+    parts: Vec<Part>,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(service: &'a SAPODataService) -> Self {
+        Batch {
+            service,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Queues a standalone read.
+    pub fn read(mut self, operation: Operation) -> Self {
+        self.parts.push(Part::Read(operation));
+        self
+    }
+
+    /// Queues a transactional group of writes.
+    pub fn change_set(mut self, operations: Vec<Operation>) -> Self {
+        self.parts.push(Part::ChangeSet(operations));
+        self
+    }
+
+    /// Sends the batch to `{service_url}/$batch` and parses the multipart
+    /// response back into one [`Result`] per queued operation, in the
+    /// order the operations were queued (change sets are flattened in
+    /// place).
+    pub async fn send(self) -> Result<Vec<Result<Value, SapError>>, SapError> {
+        let batch_boundary = unique_boundary("batch");
+        let url = format!("{}/$batch", self.service.service_url());
+        let content_type = format!("multipart/mixed; boundary={}", batch_boundary);
+        let body = self.render(&batch_boundary);
+
+        let response = self.post_batch(&url, &content_type, &body).await?;
+
+        let response_boundary = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(extract_boundary);
+
+        let (_, response_body) = read_response_body(response).await?;
+
+        let response_boundary = response_boundary.ok_or_else(|| SapError::Api {
+            code: "BATCH_RESPONSE".to_string(),
+            message: "$batch response did not declare a multipart boundary".to_string(),
+        })?;
+
+        Ok(parse_batch_response(&response_body, &response_boundary))
+    }
+
+    /// Like [`Self::send`], but deserializes each operation's JSON body
+    /// into `T`, the same ergonomics [`SAPODataService::get_entity`] gives
+    /// single-entity-set reads. Kept as a separate method rather than
+    /// making `send` itself generic, since a single batch commonly mixes
+    /// several different entity types across its queued operations --
+    /// callers whose batch is homogeneous can use this directly, and
+    /// everyone else still has `send`'s `Value`s to pick apart by hand.
+    pub async fn send_as<T: DeserializeOwned>(self) -> Result<Vec<Result<T, SapError>>, SapError> {
+        let results = self.send().await?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| {
+                result.and_then(|value| {
+                    serde_json::from_value(value).map_err(|source| SapError::Json {
+                        url: "$batch".to_string(),
+                        source,
+                    })
+                })
+            })
+            .collect())
+    }
+
+    /// POSTs the rendered batch body, attaching the cached CSRF token --
+    /// SAP requires one on every non-GET request, including `$batch`
+    /// itself -- and retrying once with a freshly fetched token if SAP
+    /// rejects the first attempt with `403` + `X-CSRF-Token: Required`.
+    async fn post_batch(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: &str,
+    ) -> Result<reqwest::Response, SapError> {
+        self.service
+            .send_with_csrf_retry(|csrf_token| {
+                self.service
+                    .http_client()
+                    .post(url)
+                    .headers(self.service.headers().clone())
+                    .header("Content-Type", content_type)
+                    .header("X-CSRF-Token", csrf_token)
+                    .body(body.to_string())
+            })
+            .await
+    }
+
+    fn render(&self, batch_boundary: &str) -> String {
+        let mut body = String::new();
+
+        for part in &self.parts {
+            body.push_str(&format!("--{}\r\n", batch_boundary));
+
+            match part {
+                Part::Read(operation) => body.push_str(&operation.render()),
+                Part::ChangeSet(operations) => {
+                    let change_set_boundary = unique_boundary("changeset");
+                    body.push_str(&format!(
+                        "Content-Type: multipart/mixed; boundary={}\r\n\r\n",
+                        change_set_boundary
+                    ));
+
+                    for operation in operations {
+                        body.push_str(&format!("--{}\r\n", change_set_boundary));
+                        body.push_str(&operation.render());
+                    }
+
+                    body.push_str(&format!("--{}--\r\n", change_set_boundary));
+                }
+            }
+        }
+
+        body.push_str(&format!("--{}--\r\n", batch_boundary));
+        body
+    }
+}
+
+/// Process-wide sequence mixed into [`unique_boundary`] so two boundaries
+/// generated within the same clock tick still can't collide -- `render`
+/// calls this once per queued change set, and hosts with coarser-than-
+/// nanosecond clock resolution (common in VMs/containers) could otherwise
+/// hand back the same `SystemTime::now()` reading twice in that loop.
+static BOUNDARY_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a boundary that won't collide with anything in the batch
+/// body, the way a UUID would, without pulling in a UUID dependency.
+fn unique_boundary(prefix: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let sequence = BOUNDARY_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    format!("{}_{:x}_{:x}", prefix, nanos, sequence)
+}
+
+fn extract_boundary(content_type: &str) -> Option<String> {
+    content_type.split(';').find_map(|segment| {
+        segment
+            .trim()
+            .strip_prefix("boundary=")
+            .map(|boundary| boundary.trim_matches('"').to_string())
+    })
+}
+
+/// Parses a `$batch` multipart response into one `Result` per operation,
+/// flattening each nested change-set group into its individual responses.
+fn parse_batch_response(body: &str, boundary: &str) -> Vec<Result<Value, SapError>> {
+    let mut results = Vec::new();
+
+    for part in split_multipart(body, boundary) {
+        match nested_boundary(part) {
+            Some(nested) => {
+                for nested_part in split_multipart(part, &nested) {
+                    results.push(parse_http_part(nested_part));
+                }
+            }
+            None => results.push(parse_http_part(part)),
+        }
+    }
+
+    results
+}
+
+fn split_multipart<'a>(body: &'a str, boundary: &str) -> Vec<&'a str> {
+    let delimiter = format!("--{}", boundary);
+    body.split(&delimiter)
+        // The text before the first delimiter is MIME preamble (for a
+        // top-level batch response, just the empty string; for a nested
+        // change-set group, the `Content-Type: multipart/mixed; ...`
+        // header introducing it) -- never a part in its own right.
+        .skip(1)
+        .map(str::trim)
+        .filter(|part| !part.is_empty() && *part != "--")
+        .collect()
+}
+
+/// If `part` is itself a nested `multipart/mixed` group (a change-set
+/// response), returns its boundary.
+fn nested_boundary(part: &str) -> Option<String> {
+    part.lines()
+        .find(|line| {
+            let line = line.to_ascii_lowercase();
+            line.starts_with("content-type:") && line.contains("multipart/mixed")
+        })
+        .and_then(extract_boundary)
+}
+
+/// Parses one `application/http` batch part (an embedded HTTP response)
+/// into its JSON body, or a [`SapError`] if the embedded status was
+/// non-2xx.
+fn parse_http_part(part: &str) -> Result<Value, SapError> {
+    let mut lines = part.lines().skip_while(|line| !line.starts_with("HTTP/"));
+
+    let status_line = lines.next().ok_or_else(|| SapError::Api {
+        code: "BATCH_PART".to_string(),
+        message: "batch response part did not contain an HTTP status line".to_string(),
+    })?;
+
+    let status_code: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| SapError::Api {
+            code: "BATCH_PART".to_string(),
+            message: format!("could not parse a status code from '{}'", status_line),
+        })?;
+
+    let mut in_body = false;
+    let mut body_lines = Vec::new();
+    for line in lines {
+        if in_body {
+            body_lines.push(line);
+        } else if line.trim().is_empty() {
+            in_body = true;
+        }
+    }
+    let body = body_lines.join("\n");
+    let body = body.trim();
+
+    if !(200..300).contains(&status_code) {
+        return Err(SapError::from_error_body("$batch", body));
+    }
+
+    if body.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    serde_json::from_str(body).map_err(|source| SapError::Json {
+        url: "$batch".to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    fn service() -> SAPODataService {
+        SAPODataService::new("https://example.com/odata", "user", "pass")
+    }
+
+    #[test]
+    fn render_wraps_reads_directly_and_change_sets_in_a_nested_boundary() {
+        let svc = service();
+        let batch = Batch::new(&svc)
+            .read(Operation::get("Entities('1')"))
+            .change_set(vec![Operation::post("Entities", json!({"Name": "A"}))]);
+
+        let body = batch.render("batch_123");
+
+        assert!(body.starts_with("--batch_123\r\n"));
+        assert!(body.contains("GET Entities('1') HTTP/1.1"));
+        assert!(body.contains("Content-Type: multipart/mixed; boundary=changeset_"));
+        assert!(body.contains("POST Entities HTTP/1.1"));
+        assert!(body.trim_end().ends_with("--batch_123--"));
+    }
+
+    #[test]
+    fn unique_boundary_never_repeats_across_many_calls() {
+        let boundaries: HashSet<_> = (0..1000).map(|_| unique_boundary("changeset")).collect();
+        assert_eq!(boundaries.len(), 1000);
+    }
+
+    #[test]
+    fn parse_batch_response_flattens_reads_and_change_sets_in_order() {
+        let body = concat!(
+            "--batch_1\r\n",
+            "Content-Type: application/http\r\n\r\n",
+            "HTTP/1.1 200 OK\r\n",
+            "Content-Type: application/json\r\n\r\n",
+            "{\"Id\":1}\r\n",
+            "--batch_1\r\n",
+            "Content-Type: multipart/mixed; boundary=changeset_1\r\n\r\n",
+            "--changeset_1\r\n",
+            "Content-Type: application/http\r\n\r\n",
+            "HTTP/1.1 201 Created\r\n",
+            "Content-Type: application/json\r\n\r\n",
+            "{\"Id\":2}\r\n",
+            "--changeset_1--\r\n",
+            "--batch_1--\r\n",
+        );
+
+        let results = parse_batch_response(body, "batch_1");
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap(), &json!({"Id": 1}));
+        assert_eq!(results[1].as_ref().unwrap(), &json!({"Id": 2}));
+    }
+
+    #[test]
+    fn parse_batch_response_surfaces_non_2xx_parts_as_errors() {
+        let body = concat!(
+            "--batch_1\r\n",
+            "Content-Type: application/http\r\n\r\n",
+            "HTTP/1.1 404 Not Found\r\n",
+            "Content-Type: application/json\r\n\r\n",
+            "{\"error\":{\"code\":\"NOT_FOUND\",\"message\":{\"value\":\"missing\"}}}\r\n",
+            "--batch_1--\r\n",
+        );
+
+        let results = parse_batch_response(body, "batch_1");
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn parse_batch_response_treats_an_empty_body_as_null() {
+        let body = concat!(
+            "--batch_1\r\n",
+            "Content-Type: application/http\r\n\r\n",
+            "HTTP/1.1 204 No Content\r\n\r\n",
+            "--batch_1--\r\n",
+        );
+
+        let results = parse_batch_response(body, "batch_1");
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap(), &Value::Null);
+    }
+}
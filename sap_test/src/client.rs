@@ -0,0 +1,646 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::batch::Batch;
+use crate::error::SapError;
+use crate::query::Query;
+
+/// Value SAP expects on the `X-CSRF-Token` header when a cached token has
+/// expired; the server echoes it back on the `403` that should trigger a
+/// re-fetch.
+pub(crate) const CSRF_TOKEN_REQUIRED: &str = "Required";
+
+/// Turns a response into its URL and body, mapping a `401` status to
+/// [`SapError::Unauthorized`] and any other non-2xx status into SAP's
+/// OData error envelope. A `401` challenge is often an HTML or empty
+/// page rather than `{"error":...}`, so it needs to be special-cased
+/// before anything tries to parse the body as one -- shared by every
+/// response-handling path (reads, writes, deletes, and `batch.rs`'s
+/// `$batch` POST) so a bad-credential or expired-session response
+/// surfaces the same way everywhere.
+pub(crate) async fn read_response_body(
+    response: reqwest::Response,
+) -> Result<(String, String), SapError> {
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(SapError::Unauthorized);
+    }
+
+    let url = response.url().to_string();
+    let status = response.status();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        return Err(SapError::from_error_body(&url, &body));
+    }
+
+    Ok((url, body))
+}
+
+/// Builds the `Accept` / `Authorization` headers shared by every client
+/// variant.
+fn default_headers(username: &str, password: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("Accept", "application/json".parse().unwrap());
+
+    let credentials = format!("{}:{}", username, password);
+    let encoded_credentials = BASE64.encode(credentials);
+    let authorization = format!("Basic {}", encoded_credentials);
+    headers.insert("Authorization", authorization.parse().unwrap());
+
+    headers
+}
+
+/// Async SAP OData client, backed by a single reused [`reqwest::Client`].
+///
+/// This is the default client: it plays nicely inside a Tokio runtime and
+/// lets callers fire off concurrent OData fetches with e.g. `join_all`. For
+/// synchronous call sites, see [`blocking::SAPODataService`].
+pub struct SAPODataService {
+    service_url: String,
+    headers: HeaderMap,
+    client: reqwest::Client,
+    csrf_token: Mutex<Option<String>>,
+}
+
+impl SAPODataService {
+    pub fn new(service_url: &str, username: &str, password: &str) -> Self {
+        SAPODataService {
+            service_url: service_url.to_string(),
+            headers: default_headers(username, password),
+            client: reqwest::Client::builder()
+                .cookie_store(true)
+                .build()
+                .expect("failed to build HTTP client"),
+            csrf_token: Mutex::new(None),
+        }
+    }
+
+    /// Like [`Self::new`], but authenticates with a client certificate
+    /// (mutual TLS) in addition to, or instead of, Basic auth. `cert_path`
+    /// must point at a PEM file containing both the certificate and its
+    /// private key, as expected by [`reqwest::Identity::from_pem`].
+    ///
+    /// Requires this crate's `rustls-tls` feature (on by default):
+    /// `Identity::from_pem` only exists under reqwest's rustls-tls
+    /// backend, not its native-tls default, so this method doesn't exist
+    /// at all when that feature is off rather than failing to compile
+    /// deep inside reqwest.
+    #[cfg(feature = "rustls-tls")]
+    pub fn with_client_certificate(
+        service_url: &str,
+        username: &str,
+        password: &str,
+        cert_path: &str,
+    ) -> Result<Self, SapError> {
+        let pem = std::fs::read(cert_path)?;
+        let identity = reqwest::Identity::from_pem(&pem)?;
+        let client = reqwest::Client::builder()
+            .cookie_store(true)
+            .identity(identity)
+            .build()?;
+
+        Ok(SAPODataService {
+            service_url: service_url.to_string(),
+            headers: default_headers(username, password),
+            client,
+            csrf_token: Mutex::new(None),
+        })
+    }
+
+    pub async fn get_data(&self, endpoint: &str) -> Result<HashMap<String, Value>, SapError> {
+        let (url, body) = self.fetch_body(endpoint).await?;
+        serde_json::from_str(&body).map_err(|source| SapError::Json { url, source })
+    }
+
+    /// Starts a [`Query`] against an entity set, e.g.
+    /// `service.entity("BusinessPartners").filter("Country eq 'US'").top(50)`.
+    pub fn entity(&self, entity_set: &str) -> Query<'_> {
+        Query::new(self, entity_set)
+    }
+
+    /// Starts a [`Batch`], bundling several reads/writes into one
+    /// `$batch` request.
+    pub fn batch(&self) -> Batch<'_> {
+        Batch::new(self)
+    }
+
+    pub(crate) fn service_url(&self) -> &str {
+        &self.service_url
+    }
+
+    pub(crate) fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub(crate) fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Like [`Self::get_data`], but deserializes each element of the OData
+    /// result set into `T` instead of leaving callers to pick apart a raw
+    /// [`serde_json::Value`]. Understands both the OData v4 envelope
+    /// (`{"value": [...]}`) and the OData v2 envelope (`{"d": {"results":
+    /// [...]}}`), unwrapping whichever one the service returns.
+    pub async fn get_entity<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> Result<Vec<T>, SapError> {
+        let (url, body) = self.fetch_body(endpoint).await?;
+        Self::parse_entity_collection(&url, &body)
+    }
+
+    /// Fetches every page of `endpoint`, following OData's continuation
+    /// link (`@odata.nextLink` in v4, `d.__next` in v2) until the server
+    /// stops returning one, and concatenates the pages into a single
+    /// `Vec<T>`. For result sets too large to buffer in memory, see
+    /// [`Self::get_all_pages`].
+    pub async fn get_all<T: DeserializeOwned>(&self, endpoint: &str) -> Result<Vec<T>, SapError> {
+        let mut items = Vec::new();
+        let mut next_url = Some(format!("{}/{}", &self.service_url, endpoint));
+
+        while let Some(url) = next_url {
+            let (page_items, next_link) = self.fetch_page(&url).await?;
+            items.extend(page_items);
+            next_url = next_link;
+        }
+
+        Ok(items)
+    }
+
+    /// Lazy, page-at-a-time counterpart to [`Self::get_all`]: each item
+    /// yielded is one page's worth of `T`, fetched only once the caller
+    /// polls for it, so a huge OData result set never has to be buffered
+    /// all at once.
+    pub fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> impl futures::Stream<Item = Result<Vec<T>, SapError>> + '_ {
+        let start_url = format!("{}/{}", &self.service_url, endpoint);
+
+        futures::stream::unfold(Some(start_url), move |next_url| async move {
+            let url = next_url?;
+            match self.fetch_page(&url).await {
+                Ok((items, next_link)) => Some((Ok(items), next_link)),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+
+    /// Issues the `GET` and returns the raw response body alongside the
+    /// URL it was fetched from, handling auth rejection and non-2xx
+    /// statuses the same way for every read path.
+    async fn fetch_body(&self, endpoint: &str) -> Result<(String, String), SapError> {
+        let url = format!("{}/{}", &self.service_url, endpoint);
+        self.fetch_body_at(&url).await
+    }
+
+    /// Like [`Self::fetch_body`], but `url` is already absolute (used to
+    /// follow OData continuation links, which aren't relative to
+    /// `service_url`).
+    async fn fetch_body_at(&self, url: &str) -> Result<(String, String), SapError> {
+        let response = self
+            .client
+            .get(url)
+            .headers(self.headers.clone())
+            .send()
+            .await?;
+
+        read_response_body(response).await
+    }
+
+    /// Fetches one page at `url` and returns its items alongside the
+    /// continuation link to the next page, if any.
+    async fn fetch_page<T: DeserializeOwned>(
+        &self,
+        url: &str,
+    ) -> Result<(Vec<T>, Option<String>), SapError> {
+        let (url, body) = self.fetch_body_at(url).await?;
+        let envelope: Value = serde_json::from_str(&body).map_err(|source| SapError::Json {
+            url: url.clone(),
+            source,
+        })?;
+        Self::parse_page(&url, &envelope)
+    }
+
+    /// Unwraps an OData v4 `value` array or an OData v2 `d.results` array
+    /// out of a response body and deserializes its elements into `T`.
+    fn parse_entity_collection<T: DeserializeOwned>(
+        url: &str,
+        body: &str,
+    ) -> Result<Vec<T>, SapError> {
+        let envelope: Value = serde_json::from_str(body).map_err(|source| SapError::Json {
+            url: url.to_string(),
+            source,
+        })?;
+
+        let (values, _next_link) = Self::envelope_values(&envelope)?;
+
+        serde_json::from_value(values.clone()).map_err(|source| SapError::Json {
+            url: url.to_string(),
+            source,
+        })
+    }
+
+    /// Like [`Self::parse_entity_collection`], but also returns the page's
+    /// continuation link, if any.
+    fn parse_page<T: DeserializeOwned>(
+        url: &str,
+        envelope: &Value,
+    ) -> Result<(Vec<T>, Option<String>), SapError> {
+        let (values, next_link) = Self::envelope_values(envelope)?;
+
+        let items = serde_json::from_value(values.clone()).map_err(|source| SapError::Json {
+            url: url.to_string(),
+            source,
+        })?;
+
+        Ok((items, next_link))
+    }
+
+    /// Locates the result array and, if present, the continuation link
+    /// inside an OData v4 (`value` / `@odata.nextLink`) or v2 (`d.results`
+    /// / `d.__next`) envelope.
+    fn envelope_values(envelope: &Value) -> Result<(&Value, Option<String>), SapError> {
+        if let Some(values) = envelope.get("value") {
+            let next_link = envelope
+                .get("@odata.nextLink")
+                .and_then(|link| link.as_str())
+                .map(str::to_string);
+            return Ok((values, next_link));
+        }
+
+        if let Some(d) = envelope.get("d") {
+            if let Some(values) = d.get("results") {
+                let next_link = d
+                    .get("__next")
+                    .and_then(|link| link.as_str())
+                    .map(str::to_string);
+                return Ok((values, next_link));
+            }
+        }
+
+        Err(SapError::Api {
+            code: "ODATA_ENVELOPE".to_string(),
+            message: "response did not contain an OData 'value' or 'd.results' array".to_string(),
+        })
+    }
+
+    /// Creates an entity with a `POST` to `endpoint`.
+    pub async fn create_entity(
+        &self,
+        endpoint: &str,
+        body: &Value,
+    ) -> Result<HashMap<String, Value>, SapError> {
+        let response = self
+            .send_write(reqwest::Method::POST, endpoint, Some(body))
+            .await?;
+        Self::parse_entity_response(response).await
+    }
+
+    /// Updates an entity with a `PUT` to `endpoint`.
+    pub async fn update_entity(
+        &self,
+        endpoint: &str,
+        body: &Value,
+    ) -> Result<HashMap<String, Value>, SapError> {
+        let response = self
+            .send_write(reqwest::Method::PUT, endpoint, Some(body))
+            .await?;
+        Self::parse_entity_response(response).await
+    }
+
+    /// Deletes the entity at `endpoint` with `DELETE`.
+    pub async fn delete_entity(&self, endpoint: &str) -> Result<(), SapError> {
+        let response = self
+            .send_write(reqwest::Method::DELETE, endpoint, None)
+            .await?;
+        read_response_body(response).await?;
+        Ok(())
+    }
+
+    /// Returns the cached CSRF token, fetching one from SAP if we don't
+    /// have one yet. Exposed to sibling modules (e.g. [`crate::batch`])
+    /// that POST directly and so need to attach the token themselves.
+    pub(crate) async fn csrf_token(&self) -> Result<String, SapError> {
+        if let Some(token) = self.csrf_token.lock().unwrap().clone() {
+            return Ok(token);
+        }
+
+        self.refresh_csrf_token().await
+    }
+
+    /// Fetches a fresh CSRF token, caches it, and returns it. Call this
+    /// after a `403` + `X-CSRF-Token: Required` invalidates the cached one.
+    pub(crate) async fn refresh_csrf_token(&self) -> Result<String, SapError> {
+        let token = self.fetch_csrf_token().await?;
+        *self.csrf_token.lock().unwrap() = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Performs SAP's token-fetch handshake: a `GET` to the service root
+    /// with `X-CSRF-Token: Fetch`, reading the token back off the response
+    /// headers. The cookie jar on `self.client` picks up the session
+    /// cookies that must accompany the token on the follow-up write.
+    async fn fetch_csrf_token(&self) -> Result<String, SapError> {
+        let response = self
+            .client
+            .get(&self.service_url)
+            .headers(self.headers.clone())
+            .header("X-CSRF-Token", "Fetch")
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(SapError::Unauthorized);
+        }
+
+        let status = response.status();
+        let token = response
+            .headers()
+            .get("X-CSRF-Token")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        if let Some(token) = token {
+            return Ok(token);
+        }
+
+        if !status.is_success() {
+            let url = response.url().to_string();
+            let body = response.text().await?;
+            return Err(SapError::from_error_body(&url, &body));
+        }
+
+        Err(SapError::Api {
+            code: "CSRF".to_string(),
+            message: "SAP did not return an X-CSRF-Token on the token fetch".to_string(),
+        })
+    }
+
+    /// Sends a write request, attaching the cached CSRF token and retrying
+    /// once with a freshly fetched token if SAP rejects the first attempt
+    /// with `403` + `X-CSRF-Token: Required`.
+    async fn send_write(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+        body: Option<&Value>,
+    ) -> Result<reqwest::Response, SapError> {
+        let url = format!("{}/{}", &self.service_url, endpoint);
+
+        self.send_with_csrf_retry(|csrf_token| {
+            let mut request = self
+                .client
+                .request(method.clone(), &url)
+                .headers(self.headers.clone())
+                .header("X-CSRF-Token", csrf_token)
+                .header("Content-Type", "application/json");
+
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+
+            request
+        })
+        .await
+    }
+
+    /// Sends a request built by `build_request`, attaching the cached CSRF
+    /// token and retrying once with a freshly fetched token if SAP rejects
+    /// the first attempt with `403` + `X-CSRF-Token: Required`. Shared by
+    /// every write path that needs the CSRF handshake -- `send_write` here
+    /// and [`crate::batch::Batch`]'s `$batch` POST -- so the retry logic
+    /// only lives in one place. `build_request` is called again on retry
+    /// with a fresh token, so it must be cheap and side-effect-free aside
+    /// from building the request.
+    pub(crate) async fn send_with_csrf_retry<F>(
+        &self,
+        build_request: F,
+    ) -> Result<reqwest::Response, SapError>
+    where
+        F: Fn(&str) -> reqwest::RequestBuilder,
+    {
+        let token = self.csrf_token().await?;
+        let response = build_request(&token).send().await?;
+
+        let token_expired = response.status() == reqwest::StatusCode::FORBIDDEN
+            && response
+                .headers()
+                .get("X-CSRF-Token")
+                .and_then(|value| value.to_str().ok())
+                == Some(CSRF_TOKEN_REQUIRED);
+
+        if !token_expired {
+            return Ok(response);
+        }
+
+        let token = self.refresh_csrf_token().await?;
+        Ok(build_request(&token).send().await?)
+    }
+
+    async fn parse_entity_response(
+        response: reqwest::Response,
+    ) -> Result<HashMap<String, Value>, SapError> {
+        let (url, body) = read_response_body(response).await?;
+        serde_json::from_str(&body).map_err(|source| SapError::Json { url, source })
+    }
+}
+
+/// Blocking variant of [`SAPODataService`], for call sites that aren't
+/// running inside an async runtime. Gated behind the `blocking` feature
+/// since it pulls in `reqwest`'s blocking client. Read-only for now; the
+/// CSRF write handshake (`create_entity`/`update_entity`/`delete_entity`)
+/// only exists on the async client.
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use super::*;
+
+    pub struct SAPODataService {
+        service_url: String,
+        headers: HeaderMap,
+        client: reqwest::blocking::Client,
+    }
+
+    impl SAPODataService {
+        pub fn new(service_url: &str, username: &str, password: &str) -> Self {
+            SAPODataService {
+                service_url: service_url.to_string(),
+                headers: default_headers(username, password),
+                client: reqwest::blocking::Client::new(),
+            }
+        }
+
+        /// Like [`Self::new`], but authenticates with a client certificate
+        /// (mutual TLS) in addition to, or instead of, Basic auth. See
+        /// [`super::SAPODataService::with_client_certificate`]; requires
+        /// the same `rustls-tls` feature for the same reason.
+        #[cfg(feature = "rustls-tls")]
+        pub fn with_client_certificate(
+            service_url: &str,
+            username: &str,
+            password: &str,
+            cert_path: &str,
+        ) -> Result<Self, SapError> {
+            let pem = std::fs::read(cert_path)?;
+            let identity = reqwest::Identity::from_pem(&pem)?;
+            let client = reqwest::blocking::Client::builder()
+                .identity(identity)
+                .build()?;
+
+            Ok(SAPODataService {
+                service_url: service_url.to_string(),
+                headers: default_headers(username, password),
+                client,
+            })
+        }
+
+        pub fn get_data(&self, endpoint: &str) -> Result<HashMap<String, Value>, SapError> {
+            let (url, body) = self.fetch_body(endpoint)?;
+            serde_json::from_str(&body).map_err(|source| SapError::Json { url, source })
+        }
+
+        /// See [`super::SAPODataService::get_entity`].
+        pub fn get_entity<T: DeserializeOwned>(&self, endpoint: &str) -> Result<Vec<T>, SapError> {
+            let (url, body) = self.fetch_body(endpoint)?;
+            super::SAPODataService::parse_entity_collection(&url, &body)
+        }
+
+        fn fetch_body(&self, endpoint: &str) -> Result<(String, String), SapError> {
+            let url = format!("{}/{}", &self.service_url, endpoint);
+
+            let response = self.client.get(&url).headers(self.headers.clone()).send()?;
+
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+                return Err(SapError::Unauthorized);
+            }
+
+            let status = response.status();
+            let body = response.text()?;
+
+            if !status.is_success() {
+                return Err(SapError::from_error_body(&url, &body));
+            }
+
+            Ok((url, body))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct TestEntity {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn parse_entity_collection_deserializes_odata_v4_value_array_into_t() {
+        let body = r#"{"value":[{"id":1,"name":"A"},{"id":2,"name":"B"}]}"#;
+
+        let entities: Vec<TestEntity> =
+            SAPODataService::parse_entity_collection("https://example.com/Entities", body).unwrap();
+
+        assert_eq!(
+            entities,
+            vec![
+                TestEntity {
+                    id: 1,
+                    name: "A".to_string()
+                },
+                TestEntity {
+                    id: 2,
+                    name: "B".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_entity_collection_deserializes_odata_v2_d_results_array_into_t() {
+        let body = r#"{"d":{"results":[{"id":1,"name":"A"}]}}"#;
+
+        let entities: Vec<TestEntity> =
+            SAPODataService::parse_entity_collection("https://example.com/Entities", body).unwrap();
+
+        assert_eq!(
+            entities,
+            vec![TestEntity {
+                id: 1,
+                name: "A".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_entity_collection_surfaces_a_json_error_when_a_field_does_not_match_t() {
+        let body = r#"{"value":[{"id":"not-a-number","name":"A"}]}"#;
+
+        let err = SAPODataService::parse_entity_collection::<TestEntity>(
+            "https://example.com/Entities",
+            body,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, SapError::Json { .. }));
+    }
+
+    #[test]
+    fn envelope_values_reads_odata_v4_value_array() {
+        let envelope = json!({
+            "value": [{"Id": 1}],
+            "@odata.nextLink": "Entities?$skiptoken=abc"
+        });
+
+        let (values, next_link) = SAPODataService::envelope_values(&envelope).unwrap();
+
+        assert_eq!(values, &json!([{"Id": 1}]));
+        assert_eq!(next_link.as_deref(), Some("Entities?$skiptoken=abc"));
+    }
+
+    #[test]
+    fn envelope_values_reads_odata_v2_d_results_array() {
+        let envelope = json!({
+            "d": {
+                "results": [{"Id": 1}],
+                "__next": "https://example.com/Entities?$skip=20"
+            }
+        });
+
+        let (values, next_link) = SAPODataService::envelope_values(&envelope).unwrap();
+
+        assert_eq!(values, &json!([{"Id": 1}]));
+        assert_eq!(
+            next_link.as_deref(),
+            Some("https://example.com/Entities?$skip=20")
+        );
+    }
+
+    #[test]
+    fn envelope_values_treats_missing_next_link_as_last_page() {
+        let envelope = json!({"value": [{"Id": 1}]});
+
+        let (_, next_link) = SAPODataService::envelope_values(&envelope).unwrap();
+
+        assert_eq!(next_link, None);
+    }
+
+    #[test]
+    fn envelope_values_rejects_envelopes_without_a_value_or_d_results_array() {
+        let envelope = json!({"error": {"code": "404", "message": {"value": "not found"}}});
+
+        let err = SAPODataService::envelope_values(&envelope).unwrap_err();
+
+        assert!(matches!(err, SapError::Api { code, .. } if code == "ODATA_ENVELOPE"));
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+/// Errors produced by [`crate::SAPODataService`].
+///
+/// Unlike a bare `reqwest::Error`, this distinguishes transport failures from
+/// JSON decoding failures, auth rejections, and SAP-side OData error
+/// payloads, and attaches the request URL to the variants where knowing
+/// *which* endpoint failed actually matters.
+#[derive(Debug)]
+pub enum SapError {
+    /// The HTTP request itself failed (connection, TLS, timeout, ...).
+    Http { url: String, source: reqwest::Error },
+    /// The response body could not be parsed as JSON.
+    Json {
+        url: String,
+        source: serde_json::Error,
+    },
+    /// The server responded `401 Unauthorized`.
+    Unauthorized,
+    /// A local I/O error, e.g. reading a client certificate from disk.
+    Io(std::io::Error),
+    /// SAP responded with its own OData error envelope:
+    /// `{"error":{"code":..., "message":{"value":...}}}`.
+    Api { code: String, message: String },
+}
+
+impl fmt::Display for SapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SapError::Http { url, source } => write!(f, "request to {} failed: {}", url, source),
+            SapError::Json { url, source } => {
+                write!(
+                    f,
+                    "failed to decode response from {} as JSON: {}",
+                    url, source
+                )
+            }
+            SapError::Unauthorized => write!(f, "request rejected: unauthorized"),
+            SapError::Io(source) => write!(f, "I/O error: {}", source),
+            SapError::Api { code, message } => write!(f, "SAP error {}: {}", code, message),
+        }
+    }
+}
+
+impl std::error::Error for SapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SapError::Http { source, .. } => Some(source),
+            SapError::Json { source, .. } => Some(source),
+            SapError::Io(source) => Some(source),
+            SapError::Unauthorized | SapError::Api { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SapError {
+    fn from(source: reqwest::Error) -> Self {
+        let url = source
+            .url()
+            .map(|u| u.to_string())
+            .unwrap_or_else(|| "<unknown>".to_string());
+        SapError::Http { url, source }
+    }
+}
+
+impl From<std::io::Error> for SapError {
+    fn from(source: std::io::Error) -> Self {
+        SapError::Io(source)
+    }
+}
+
+/// SAP's OData error envelope, e.g.:
+/// `{"error":{"code":"ABC/123","message":{"lang":"en","value":"..."}}}`.
+#[derive(Debug, Deserialize)]
+struct ErrorEnvelope {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    code: String,
+    message: ErrorMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorMessage {
+    value: String,
+}
+
+impl SapError {
+    /// Builds a [`SapError::Api`] from a non-2xx response body, falling back
+    /// to a [`SapError::Json`] if the body doesn't match SAP's error
+    /// envelope shape.
+    pub(crate) fn from_error_body(url: &str, body: &str) -> Self {
+        match serde_json::from_str::<ErrorEnvelope>(body) {
+            Ok(envelope) => SapError::Api {
+                code: envelope.error.code,
+                message: envelope.error.message.value,
+            },
+            Err(source) => SapError::Json {
+                url: url.to_string(),
+                source,
+            },
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! A minimal client for SAP OData services.
+
+pub mod batch;
+pub mod client;
+pub mod error;
+pub mod query;
+
+pub use batch::{Batch, Operation};
+pub use client::SAPODataService;
+pub use error::SapError;
+pub use query::Query;
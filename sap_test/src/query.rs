@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::client::SAPODataService;
+use crate::error::SapError;
+
+/// Fluent builder for OData system query options (`$filter`, `$select`,
+/// `$top`, `$skip`, `$expand`, `$orderby`), returned by
+/// [`SAPODataService::entity`].
+///
+/// Accumulating options through this builder (rather than hand-concatenating
+/// the endpoint string) gets URL encoding for free, so spaces and quoted
+/// string literals in a `$filter` survive the trip intact.
+pub struct Query<'a> {
+    service: &'a SAPODataService,
+    entity_set: String,
+    filter: Option<String>,
+    select: Vec<String>,
+    expand: Vec<String>,
+    order_by: Vec<String>,
+    top: Option<u32>,
+    skip: Option<u32>,
+}
+
+impl<'a> Query<'a> {
+    pub(crate) fn new(service: &'a SAPODataService, entity_set: &str) -> Self {
+        Query {
+            service,
+            entity_set: entity_set.to_string(),
+            filter: None,
+            select: Vec::new(),
+            expand: Vec::new(),
+            order_by: Vec::new(),
+            top: None,
+            skip: None,
+        }
+    }
+
+    /// Sets `$filter`, e.g. `"Country eq 'US'"`.
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Adds fields to `$select`.
+    pub fn select<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.select.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds navigation properties to `$expand`.
+    pub fn expand<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.expand.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds fields to `$orderby`.
+    pub fn order_by<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.order_by.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets `$top`.
+    pub fn top(mut self, top: u32) -> Self {
+        self.top = Some(top);
+        self
+    }
+
+    /// Sets `$skip`.
+    pub fn skip(mut self, skip: u32) -> Self {
+        self.skip = Some(skip);
+        self
+    }
+
+    /// Renders the accumulated query options into `entity_set?$filter=...`,
+    /// URL-encoding every option value.
+    fn build(&self) -> String {
+        let mut options: Vec<(&str, String)> = Vec::new();
+
+        if let Some(filter) = &self.filter {
+            options.push(("$filter", filter.clone()));
+        }
+        if !self.select.is_empty() {
+            options.push(("$select", self.select.join(",")));
+        }
+        if !self.expand.is_empty() {
+            options.push(("$expand", self.expand.join(",")));
+        }
+        if !self.order_by.is_empty() {
+            options.push(("$orderby", self.order_by.join(",")));
+        }
+        if let Some(top) = self.top {
+            options.push(("$top", top.to_string()));
+        }
+        if let Some(skip) = self.skip {
+            options.push(("$skip", skip.to_string()));
+        }
+
+        if options.is_empty() {
+            return self.entity_set.clone();
+        }
+
+        let query_string = options
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, encode_query_value(&value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        format!("{}?{}", self.entity_set, query_string)
+    }
+
+    /// Runs the query, returning the raw OData envelope as a
+    /// `HashMap<String, Value>`. See [`SAPODataService::get_data`].
+    pub async fn get(&self) -> Result<HashMap<String, Value>, SapError> {
+        self.service.get_data(&self.build()).await
+    }
+
+    /// Runs the query, deserializing the result set into `T`. See
+    /// [`SAPODataService::get_entity`].
+    pub async fn get_as<T: DeserializeOwned>(&self) -> Result<Vec<T>, SapError> {
+        self.service.get_entity(&self.build()).await
+    }
+}
+
+/// Percent-encodes a query option value, leaving only RFC 3986 unreserved
+/// characters (`A-Za-z0-9-_.~`) unescaped. This is deliberately
+/// conservative: it also escapes delimiters like `,` and `'` so `$filter`
+/// string literals and comma-separated `$select`/`$expand` lists always
+/// round-trip, rather than relying on which separators a given SAP gateway
+/// tolerates unescaped.
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_query_value_escapes_spaces_and_quotes() {
+        assert_eq!(
+            encode_query_value("Country eq 'US'"),
+            "Country%20eq%20%27US%27"
+        );
+    }
+
+    #[test]
+    fn encode_query_value_leaves_unreserved_characters_alone() {
+        assert_eq!(encode_query_value("Name-City_v1.0~a"), "Name-City_v1.0~a");
+    }
+
+    #[test]
+    fn encode_query_value_escapes_commas() {
+        assert_eq!(encode_query_value("Name,City"), "Name%2CCity");
+    }
+
+    #[test]
+    fn build_renders_no_query_string_when_no_options_are_set() {
+        let service = SAPODataService::new("https://example.com/odata", "user", "pass");
+        assert_eq!(
+            Query::new(&service, "BusinessPartners").build(),
+            "BusinessPartners"
+        );
+    }
+
+    #[test]
+    fn build_joins_multiple_options_with_ampersand_and_url_encodes_each() {
+        let service = SAPODataService::new("https://example.com/odata", "user", "pass");
+        let query = Query::new(&service, "BusinessPartners")
+            .filter("Country eq 'US'")
+            .select(["Name", "City"])
+            .top(50);
+
+        assert_eq!(
+            query.build(),
+            "BusinessPartners?$filter=Country%20eq%20%27US%27&$select=Name%2CCity&$top=50"
+        );
+    }
+}